@@ -55,6 +55,118 @@ async fn make_insert(client: &Client, data: &[SomeRow]) -> Result<()> {
     insert.end().await
 }
 
+/// 用服务端有类型的命名参数查询数据。
+///
+/// 参数:
+/// - `client`: ClickHouse 客户端实例。
+/// - `name`: 要绑定给 `{name:String}` 的值。
+///
+/// 返回:
+/// - `Result<Vec<SomeRow>>`: 包含查询结果的行向量。
+async fn make_select_with_param(client: &Client, name: &str) -> Result<Vec<SomeRow>> {
+    client
+        .query("SELECT ?fields FROM `who cares` WHERE name = {name:String}")
+        .param("name", name)
+        .fetch_all::<SomeRow>()
+        .await
+}
+
+/// 通过 `.bind()` 把一个带引号/反斜杠的值转义进 SQL 字面量后执行，用于
+/// 检验 `.bind()` 的客户端转义逻辑本身——这是用户输入和 SQL 注入之间
+/// 唯一的屏障，值得单独验证，而不能只靠 `.param()` 那条路径的测试覆盖。
+async fn make_execute_with_bind(client: &Client, value: &str) -> Result<()> {
+    client
+        .query("ALTER TABLE `who cares` DELETE WHERE name = ?")
+        .bind(value)
+        .execute()
+        .await
+}
+
+/// 和 [`make_select`] 一样用 `fetch_all`，用于配合下面截断过的响应体，
+/// 检验响应在一行数据中间被截断时会不会被误当成"正好读完了"。
+async fn make_select_all(client: &Client) -> Result<Vec<SomeRow>> {
+    client
+        .query("SELECT ?fields FROM `who cares`")
+        .fetch_all::<SomeRow>()
+        .await
+}
+
+/// 用 Arrow 格式取回结果，统计所有 batch 加起来一共有多少行。
+///
+/// 参数:
+/// - `client`: ClickHouse 客户端实例。
+///
+/// 返回:
+/// - `Result<usize>`: 所有 `RecordBatch` 加起来的行数。
+async fn make_fetch_arrow(client: &Client) -> Result<usize> {
+    let mut cursor = client
+        .query("SELECT ?fields FROM `who cares`")
+        .fetch_arrow()?;
+
+    let mut rows = 0;
+    while let Some(batch) = cursor.next().await? {
+        rows += batch.num_rows();
+    }
+    Ok(rows)
+}
+
+/// 用 `with_timeout` 发起一次查询，读完全部行，并把遇到过的 `query_id`
+/// 带回来——用于检验设了超时的正常查询依然能顺利读完，且在读完之后不会
+/// 再对一个已经结束的查询补发 `KILL QUERY`。
+async fn make_select_with_timeout(client: &Client) -> Result<(String, Vec<SomeRow>)> {
+    let mut cursor = client
+        .query("SELECT ?fields FROM `who cares`")
+        .with_timeout(std::time::Duration::from_secs(30))
+        .fetch::<SomeRow>()?;
+
+    let query_id = cursor.query_id().to_string();
+    let mut rows = Vec::new();
+    while let Some(row) = cursor.next().await? {
+        rows.push(row);
+    }
+    Ok((query_id, rows))
+}
+
+/// 用 `with_progress` 发起一次查询，读完全部行后返回 `stats()` 快照，
+/// 用于检验 `Stats` 是真的从 `Response` 的 header 解析里取数，而不是一个
+/// 摆设——`result_rows`/`result_bytes` 在流结束时应该能从
+/// `X-ClickHouse-Summary` 里拿到非零值。
+async fn make_select_with_stats(client: &Client) -> Result<(Vec<SomeRow>, clickhouse::query::Stats)> {
+    let mut cursor = client
+        .query("SELECT ?fields FROM `who cares`")
+        .with_progress()
+        .fetch::<SomeRow>()?;
+
+    let mut rows = Vec::new();
+    while let Some(row) = cursor.next().await? {
+        rows.push(row);
+    }
+    Ok((rows, cursor.stats()))
+}
+
+/// 把 `rows` 编码成一段完整的 Arrow IPC stream 字节流，供 mock 直接回放，
+/// 从而练到 `fetch_arrow` 的增量解码逻辑。
+fn arrow_ipc_stream_bytes(rows: &[SomeRow]) -> Vec<u8> {
+    use std::sync::Arc;
+
+    use arrow::{
+        array::UInt32Array,
+        datatypes::{DataType, Field, Schema},
+        ipc::writer::StreamWriter,
+        record_batch::RecordBatch,
+    };
+
+    let schema = Arc::new(Schema::new(vec![Field::new("no", DataType::UInt32, false)]));
+    let array = UInt32Array::from(rows.iter().map(|row| row.no).collect::<Vec<_>>());
+    let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(array)]).unwrap();
+
+    let mut buffer = Vec::new();
+    let mut writer = StreamWriter::try_new(&mut buffer, &schema).unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+    buffer
+}
+
 /// 监视表的变更，并在数据变更时获取最新的数据。
 ///
 /// `make_watch` 函数用于监视 `test` 表的数据变更。它通过 `watch` 查询来订阅表的变更事件，并在数据变更时获取最新的数据。
@@ -90,6 +202,30 @@ async fn make_watch_only_events(client: &Client) -> Result<u64> {
         .await
 }
 
+/// 按版本号分组监视表的变更。
+///
+/// `make_watch_groups` 函数用于监视 `test` 表的数据变更，但和
+/// `make_watch` 不同，它把同一个版本号下的所有行攒成一组一起返回。
+///
+/// 参数:
+/// - `client`: ClickHouse 客户端实例。
+///
+/// 返回:
+/// - `Result<Vec<(u64, Vec<SomeRow>)>>`: 每一次刷新的版本号和对应的所有行。
+#[cfg(feature = "watch")]
+async fn make_watch_groups(client: &Client) -> Result<Vec<(u64, Vec<SomeRow>)>> {
+    let mut cursor = client
+        .watch("SELECT max(no) no FROM test")
+        .groups()
+        .fetch::<SomeRow>()?;
+
+    let mut groups = Vec::new();
+    while let Some(group) = cursor.next().await? {
+        groups.push(group);
+    }
+    Ok(groups)
+}
+
 #[tokio::main]
 async fn main() {
     let mock = test::Mock::new();
@@ -111,6 +247,53 @@ async fn main() {
     let reason = make_select(&client).await;
     assert_eq!(format!("{reason:?}"), r#"Err(BadResponse("Forbidden"))"#);
 
+    // 如何测试 `fetch_all` 在响应于一行数据中间被截断时会报错，而不是
+    // 悄悄地把已经读到的行当成完整结果返回。`SomeRow` 只有一个 `u32`
+    // 字段，完整一行是 4 字节，这里只给 2 字节。
+    mock.add(test::handlers::provide_raw(vec![0x01, 0x00]));
+    let reason = make_select_all(&client).await;
+    assert!(matches!(reason, Err(err) if err.to_string().contains("truncated")));
+
+    // 如何测试带命名参数的查询，包括值里带引号这种容易踩坑的输入。
+    mock.add(test::handlers::provide(stream::iter(list.clone())));
+    let rows = make_select_with_param(&client, "a'b").await.unwrap();
+    assert_eq!(rows, list);
+
+    // 如何测试 `.bind()` 本身的转义：服务端收到的应该是转义后的 SQL
+    // 字面量（`'` 转成 `\'`，`\` 转成 `\\`），而不是原始未转义的输入。
+    let recording = mock.add(test::handlers::record_ddl());
+    make_execute_with_bind(&client, "a'b\\c").await.unwrap();
+    let query = recording.query().await;
+    assert!(query.contains(r"'a\'b\\c'"));
+
+    // 如何测试 `with_timeout`：设了超时的查询正常读完时不受影响，
+    // `query_id()` 能照常拿到这次查询生成的 id。
+    mock.add(test::handlers::provide(stream::iter(list.clone())));
+    let (query_id, rows) = make_select_with_timeout(&client).await.unwrap();
+    assert!(!query_id.is_empty());
+    assert_eq!(rows, list);
+
+    // 如何测试 `with_progress`/`stats`：流读完之后 `stats()` 应该能从响应
+    // header 里看到非零的 `result_rows`/`result_bytes`。
+    mock.add(test::handlers::provide(stream::iter(list.clone())));
+    let (rows, stats) = make_select_with_stats(&client).await.unwrap();
+    assert_eq!(rows, list);
+    assert_eq!(stats.result_rows, list.len() as u64);
+    assert!(stats.result_bytes > 0);
+
+    // 如何测试 `fetch_arrow`：mock 直接回放一段编码好的 Arrow IPC 字节流。
+    mock.add(test::handlers::provide_raw(arrow_ipc_stream_bytes(&list)));
+    let rows = make_fetch_arrow(&client).await.unwrap();
+    assert_eq!(rows, list.len());
+
+    // 如何测试 `fetch_arrow` 在 Arrow IPC 流于一条消息中间被截断时会报错，
+    // 而不是把剩下的半条消息当成干净的流末尾悄悄扔掉。
+    let mut truncated = arrow_ipc_stream_bytes(&list);
+    truncated.truncate(truncated.len() - 4);
+    mock.add(test::handlers::provide_raw(truncated));
+    let reason = make_fetch_arrow(&client).await;
+    assert!(matches!(reason, Err(err) if err.to_string().contains("truncated")));
+
     // 如何测试 INSERT 操作。
     let recording = mock.add(test::handlers::record());
     make_insert(&client, &list).await.unwrap();
@@ -136,5 +319,22 @@ async fn main() {
         let version = make_watch_only_events(&client).await.unwrap();
         assert!(recording.query().await.contains("CREATE LIVE VIEW"));
         assert_eq!(version, 3);
+
+        // 按版本号分组的 `WATCH`：同一版本号下的多行应该被攒成一组返回。
+        let recording = mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::watch(stream::iter([
+            (42, SomeRow { no: 1 }),
+            (42, SomeRow { no: 2 }),
+            (43, SomeRow { no: 3 }),
+        ])));
+        let groups = make_watch_groups(&client).await.unwrap();
+        assert!(recording.query().await.contains("CREATE LIVE VIEW"));
+        assert_eq!(
+            groups,
+            vec![
+                (42, vec![SomeRow { no: 1 }, SomeRow { no: 2 }]),
+                (43, vec![SomeRow { no: 3 }]),
+            ]
+        );
     }
 }
@@ -0,0 +1,119 @@
+use std::marker::PhantomData;
+
+use bytes::{Buf, BytesMut};
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Result},
+    query::Stats,
+    response::Response,
+    rowbinary,
+};
+
+/// 对 `FORMAT RowBinary` 响应逐行增量解码的游标，被 [`crate::query::RowCursor`]
+/// 包裹使用。
+pub(crate) struct RowBinaryCursor<T> {
+    response: Response,
+    buffer: BytesMut,
+    _marker: PhantomData<T>,
+}
+
+impl<T> RowBinaryCursor<T> {
+    pub(crate) fn new(response: Response) -> Self {
+        Self {
+            response,
+            buffer: BytesMut::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) async fn next<'a, 'b: 'a>(&'a mut self) -> Result<Option<T>>
+    where
+        T: Deserialize<'b>,
+    {
+        loop {
+            if let Some(row) = self.try_decode_one()? {
+                return Ok(Some(row));
+            }
+
+            match self.response.chunk().await? {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => {
+                    if !self.buffer.is_empty() {
+                        return Err(Error::Decode("truncated RowBinary stream".into()));
+                    }
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn try_decode_one<'a, 'b: 'a>(&'a mut self) -> Result<Option<T>>
+    where
+        T: Deserialize<'b>,
+    {
+        let mut slice = &self.buffer[..];
+        match rowbinary::deserialize_from::<T>(&mut slice) {
+            Ok(row) => {
+                let consumed = self.buffer.len() - slice.len();
+                self.buffer.advance(consumed);
+                Ok(Some(row))
+            }
+            Err(Error::NotEnoughData) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// 目前为止这次查询的进度/结果统计快照，见 [`crate::query::RowCursor::stats`]。
+    pub(crate) fn stats(&self) -> Stats {
+        self.response.stats()
+    }
+}
+
+/// 对 `WATCH` 查询的 JSONEachRow 风格响应逐行增量解码的游标，被
+/// [`crate::watch::WatchCursor`] 包裹使用。每一行是一个独立的 JSON 对象，
+/// 以换行符分隔；每一行交给 `serde_json` 解码。
+pub(crate) struct JsonCursor<T> {
+    response: Response,
+    buffer: BytesMut,
+    _marker: PhantomData<T>,
+}
+
+impl<T> JsonCursor<T> {
+    pub(crate) fn new(response: Response) -> Self {
+        Self {
+            response,
+            buffer: BytesMut::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) async fn next<'a, 'b: 'a>(&'a mut self) -> Result<Option<T>>
+    where
+        T: Deserialize<'b>,
+    {
+        loop {
+            if let Some(pos) = self.buffer.iter().position(|&byte| byte == b'\n') {
+                let line = self.buffer.split_to(pos);
+                self.buffer.advance(1); // 跳过换行符本身。
+
+                let row = serde_json::from_slice(&line)
+                    .map_err(|err| Error::Decode(err.to_string()))?;
+                return Ok(Some(row));
+            }
+
+            match self.response.chunk().await? {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => {
+                    if self.buffer.is_empty() {
+                        return Ok(None);
+                    }
+                    let line = std::mem::take(&mut self.buffer);
+                    let row = serde_json::from_slice(&line)
+                        .map_err(|err| Error::Decode(err.to_string()))?;
+                    return Ok(Some(row));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+
+/// 这个 crate 里统一的错误类型。
+#[derive(Debug)]
+pub enum Error {
+    /// 期望至少一行结果，但流是空的。
+    RowNotFound,
+    /// 构造请求时参数有问题（URL、header 等）。
+    InvalidParams(Box<dyn std::error::Error + Send + Sync>),
+    /// 服务端返回了一个非成功的响应。
+    BadResponse(String),
+    /// 反序列化时，当前缓冲区里的字节还不够解出一条完整的记录/消息，
+    /// 需要等待更多数据到达——不代表数据本身有问题，调用方应该据此
+    /// 去拉取下一个网络 chunk，而不是当成真正的解码失败处理。
+    NotEnoughData,
+    /// 反序列化过程中遇到了真正的数据问题（类型不匹配、格式错误、
+    /// 流提前结束等），需要如实地传播给调用方，不能被悄悄吞掉。
+    Decode(String),
+    /// 底层 I/O 出错。
+    Io(std::io::Error),
+    /// 后台任务 panic 或被取消。
+    TaskAborted(tokio::task::JoinError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::RowNotFound => write!(f, "row not found"),
+            Error::InvalidParams(err) => write!(f, "invalid params: {err}"),
+            Error::BadResponse(msg) => write!(f, "bad response: {msg}"),
+            Error::NotEnoughData => write!(f, "not enough data"),
+            Error::Decode(msg) => write!(f, "failed to decode response: {msg}"),
+            Error::Io(err) => write!(f, "io error: {err}"),
+            Error::TaskAborted(err) => write!(f, "background task aborted: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Error::TaskAborted(err)
+    }
+}
+
+/// 这个 crate 里统一的 `Result` 别名。
+pub type Result<T, E = Error> = std::result::Result<T, E>;
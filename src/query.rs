@@ -1,25 +1,52 @@
+use std::{collections::HashMap, io::Cursor as IoCursor, sync::Arc, time::Duration};
+
+use arrow::{
+    array::ArrayRef,
+    datatypes::SchemaRef,
+    ipc::{
+        convert::fb_to_schema,
+        reader::{read_dictionary, read_record_batch},
+        root_as_message, MessageHeader,
+    },
+    record_batch::RecordBatch,
+};
+use bytes::{Buf, Bytes, BytesMut};
 use hyper::{header::CONTENT_LENGTH, Body, Method, Request};
 use serde::Deserialize;
-use tokio::task;
+use tokio::{sync::mpsc, task, time::sleep};
 use url::Url; // 用于并发任务
+use uuid::Uuid;
 
 use crate::{
     cursor::RowBinaryCursor,
     error::{Error, Result},
     response::Response,
     row::Row,
+    rowbinary,
     sql::{Bind, SqlBuilder},
     Client,
 };
 
+/// Arrow IPC 流格式中的 continuation marker（`0xFFFFFFFF`）。ClickHouse
+/// 的 `ArrowStream` 输出总是带着它，所以这里不处理没有 marker 的旧版
+/// 格式——与其留一条算错偏移量的死代码路径，不如直接要求它存在。
+const ARROW_CONTINUATION_MARKER: u32 = 0xFFFF_FFFF;
+
 const MAX_QUERY_LEN_TO_USE_GET: usize = 8192;
-const BUFFER_SIZE: usize = 20000; // 缓冲区大小
+
+/// `fetch_all` 流水线中「网络读取」阶段与「解码」阶段之间的有界 channel 容量，
+/// 即网络读取阶段最多可以领先解码阶段多少个原始字节块。容量越大，内存占用越高，
+/// 但越能平滑掉解码端偶尔的抖动。
+const BUFFER_SIZE: usize = 16;
 
 #[must_use]
 #[derive(Clone)]
 pub struct Query {
     client: Client,
     sql: SqlBuilder,
+    params: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    progress: bool,
 }
 
 impl Query {
@@ -27,9 +54,32 @@ impl Query {
         Self {
             client: client.clone(),
             sql: SqlBuilder::new(template),
+            params: Vec::new(),
+            timeout: None,
+            progress: false,
         }
     }
 
+    /// 给这次查询设置一个超时时间。
+    ///
+    /// 超时后会在一个新连接上对生成的 `query_id` 发起一次尽力而为的
+    /// `KILL QUERY`，让服务端尽早停止计算，而不是任由一个被调用方放弃的
+    /// 流继续占用服务端资源直到它自然结束。游标被提前 drop 掉（还没读到
+    /// 流末尾）时也会触发同样的 `KILL QUERY`。
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 打开 `send_progress_in_http_headers` 设置，让服务端通过
+    /// `X-ClickHouse-Progress` header 上报进度。[`RowCursor::stats`] 只在
+    /// 响应开始时（响应头部）和响应体读完时（trailer）合并这些 header，
+    /// 在这两个时刻之间逐行读取期间，`read_rows`/`read_bytes` 不会变化。
+    pub fn with_progress(mut self) -> Self {
+        self.progress = true;
+        self
+    }
+
     /// 绑定值到查询中的下一个 `?`。
     ///
     /// `value` 必须实现 [`Serialize`](serde::Serialize) 或者是一个 [`Identifier`]，并且会被适当地转义。
@@ -40,6 +90,22 @@ impl Query {
         self
     }
 
+    /// 绑定一个服务端有类型的命名参数，对应 SQL 里的 `{name:Type}` 占位符，
+    /// 例如 `SELECT * FROM t WHERE name = {name:String}`。
+    ///
+    /// 和 [`Query::bind`] 在客户端把值转义进 SQL 文本不同，命名参数是作为
+    /// `param_<name>=<value>` 这样的 URL 查询参数单独传给服务端的，由
+    /// ClickHouse 自己解析、做类型检查，因此可以安全地被复用和缓存，
+    /// 不必担心客户端转义不当带来的注入问题。两种绑定方式可以同时使用。
+    pub fn param(mut self, name: &str, value: impl Bind) -> Self {
+        let mut text = String::new();
+        // 命名参数走服务端的裸文本格式，不是 SQL 字面量，所以用
+        // `write_param`（不加引号/不转义），不能像 `bind()` 那样用 `write`。
+        let _ = value.write_param(&mut text);
+        self.params.push((name.to_string(), text));
+        self
+    }
+
     /// 执行查询。
     pub async fn execute(self) -> Result<()> {
         self.do_execute(false)?.finish().await
@@ -70,8 +136,53 @@ impl Query {
         self.sql.bind_fields::<T>();
         self.sql.append(" FORMAT RowBinary");
 
+        let client = self.client.clone();
+        let timeout = self.timeout;
+        let (response, query_id) = self.do_execute_with_id(true)?;
+
+        let timeout_task = timeout.map(|duration| {
+            let client = client.clone();
+            let query_id = query_id.clone();
+            task::spawn(async move {
+                sleep(duration).await;
+                kill_query(client, query_id).await;
+            })
+        });
+
+        Ok(RowCursor {
+            inner: RowBinaryCursor::new(response),
+            client,
+            query_id,
+            timeout_task,
+            cancel_on_drop: timeout.is_some(),
+            finished: false,
+        })
+    }
+
+    /// 以 Arrow [`RecordBatch`] 的形式流式获取查询结果。
+    ///
+    /// 与 [`Query::fetch`] 按行反序列化不同，这里让服务端以
+    /// `FORMAT ArrowStream` 返回结果，游标直接对 Arrow IPC 流增量解码，
+    /// 省去逐行 serde 往返，更适合分析型查询后接 `arrow`/`parquet` 生态。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # async fn example() -> clickhouse::error::Result<()> {
+    /// let mut cursor = clickhouse::Client::default()
+    ///     .query("SELECT ?fields FROM some WHERE no BETWEEN 0 AND 1")
+    ///     .fetch_arrow()?;
+    ///
+    /// while let Some(batch) = cursor.next().await? {
+    ///     println!("{} rows", batch.num_rows());
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn fetch_arrow(mut self) -> Result<ArrowCursor> {
+        self.sql.append(" FORMAT ArrowStream");
+
         let response = self.do_execute(true)?;
-        Ok(RowCursor(RowBinaryCursor::new(response)))
+        Ok(ArrowCursor::new(response))
     }
 
     /// 执行查询并仅返回一行。
@@ -102,40 +213,115 @@ impl Query {
     ///
     /// 注意 `T` 必须是拥有所有权的类型。
     ///
-    /// # 缓冲区的意义
+    /// # 流水线
     ///
-    /// 在并行处理中，缓冲区用于暂存从数据库获取的行数据。通过将数据分块处理，可以提高并发性能，减少处理延迟。
-    /// 这里的 `BUFFER_SIZE` 表示每个缓冲区的大小，即每次并行处理的行数。增大缓冲区大小可以减少并发任务的频率，
-    /// 但同时也会增加内存使用。因此，选择合适的缓冲区大小是优化性能和资源使用的关键。
-    pub async fn fetch_all<T>(self) -> Result<Vec<T>>
+    /// 网络读取和反序列化被拆成两个阶段重叠执行：一个任务只负责从
+    /// `Response` 里拉取原始的 RowBinary 字节块，通过一个有界的
+    /// `tokio::sync::mpsc` channel（容量见 [`BUFFER_SIZE`]，有界是为了让
+    /// channel 起到背压的作用，避免解码跟不上时无限攒内存）转交给另一个
+    /// 跑在 `spawn_blocking` 上的任务；后者按到达顺序把字节块反序列化成
+    /// `T` 并依次追加到结果 `Vec` 中，从而保证行的顺序不变。解码是 CPU
+    /// 密集型工作，放到阻塞线程池上可以避免占用 async reactor 的线程，
+    /// 让网络读取和解码真正重叠起来，而不是像之前那样先读完全部行再
+    /// 原地把 buffer 搬进一个什么都不做的 `task::spawn` 里。
+    pub async fn fetch_all<T>(mut self) -> Result<Vec<T>>
     where
         T: Row + for<'b> Deserialize<'b> + std::marker::Send + 'static,
     {
-        let mut cursor = self.fetch::<T>()?;
-        let mut result = Vec::new();
+        self.sql.bind_fields::<T>();
+        self.sql.append(" FORMAT RowBinary");
+
+        let client = self.client.clone();
+        let timeout = self.timeout;
+        let (response, query_id) = self.do_execute_with_id(true)?;
 
-        // 使用缓冲区并行获取行数据，并保持顺序
-        let mut buffer = Vec::with_capacity(BUFFER_SIZE);
+        // 和 `fetch()` 一样：超时就在另一条连接上对这次的 `query_id` 发起
+        // 一次尽力而为的 `KILL QUERY`，不能因为走的是 `fetch_all` 这条
+        // 路径就让 `with_timeout` 变成没用的摆设。
+        let timeout_task = timeout.map(|duration| {
+            let client = client.clone();
+            let query_id = query_id.clone();
+            task::spawn(async move {
+                sleep(duration).await;
+                kill_query(client, query_id).await;
+            })
+        });
 
-        while let Some(row) = cursor.next().await? {
-            buffer.push(row);
+        let (tx, mut rx) = mpsc::channel::<Bytes>(BUFFER_SIZE);
 
-            if buffer.len() >= BUFFER_SIZE {
-                let chunk = buffer.split_off(0);
-                let chunk_result = task::spawn(async move { chunk })
-                    .await
-                    .map_err(Error::from)?;
-                result.extend(chunk_result);
+        // 阶段一：只管从网络读字节，不做任何反序列化。
+        let reader = task::spawn(async move {
+            let mut response = response;
+            while let Some(chunk) = response.chunk().await? {
+                if tx.send(chunk).await.is_err() {
+                    break; // 解码端已经提前退出了（多半是出错了）。
+                }
             }
+            Result::<()>::Ok(())
+        });
+
+        // 阶段二：反序列化是 CPU 密集型工作，放到阻塞线程池上做。
+        let decoder = task::spawn_blocking(move || -> Result<Vec<T>> {
+            let mut buffer = BytesMut::new();
+            let mut result = Vec::new();
+
+            while let Some(chunk) = rx.blocking_recv() {
+                buffer.extend_from_slice(&chunk);
+
+                loop {
+                    let mut slice = &buffer[..];
+                    match rowbinary::deserialize_from::<T>(&mut slice) {
+                        Ok(row) => {
+                            let consumed = buffer.len() - slice.len();
+                            result.push(row);
+                            buffer.advance(consumed);
+                        }
+                        // 缓冲区里还不够一整行，等下一个字节块；这不是数据
+                        // 有问题，只是还没收全。
+                        Err(Error::NotEnoughData) => break,
+                        // 其它错误说明数据是真的解码不了了（类型不匹配、
+                        // 流本身就是坏的），必须如实传播，不能当成“还没
+                        // 收全”而悄悄吞掉。
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+
+            if !buffer.is_empty() {
+                // 流已经结束，但缓冲区里还剩下解不完整的数据，说明响应在
+                // 一行中间被截断了，不能假装什么都没发生地返回已经读到的行。
+                return Err(Error::Decode("truncated RowBinary stream".into()));
+            }
+
+            Ok(result)
+        });
+
+        let (reader, decoder) = tokio::join!(reader, decoder);
+
+        // 流（无论成功与否）已经完整跑完了，不需要再让超时任务去 kill 一个
+        // 已经结束的查询。
+        if let Some(task) = timeout_task {
+            task.abort();
         }
 
-        // 获取缓冲区中剩余的行数据
-        result.extend(buffer);
+        reader.map_err(Error::from)??;
+        decoder.map_err(Error::from)?
+    }
 
-        Ok(result)
+    /// 和 [`Query::do_execute`] 一样执行查询，但额外生成并返回这次请求的
+    /// `query_id`，调用方可以用它之后对服务端发起 `KILL QUERY`。
+    pub(crate) fn do_execute_with_id(self, read_only: bool) -> Result<(Response, String)> {
+        let query_id = Uuid::new_v4().to_string();
+        let response = self.do_execute_impl(read_only, &query_id)?;
+        Ok((response, query_id))
     }
 
     pub(crate) fn do_execute(self, read_only: bool) -> Result<Response> {
+        let query_id = Uuid::new_v4().to_string();
+        self.do_execute_impl(read_only, &query_id)
+    }
+
+    fn do_execute_impl(self, read_only: bool, query_id: &str) -> Result<Response> {
         let query = self.sql.finish()?;
 
         let mut url =
@@ -143,6 +329,12 @@ impl Query {
         let mut pairs = url.query_pairs_mut();
         pairs.clear();
 
+        pairs.append_pair("query_id", query_id);
+
+        if self.progress {
+            pairs.append_pair("send_progress_in_http_headers", "1");
+        }
+
         if let Some(database) = &self.client.database {
             pairs.append_pair("database", database);
         }
@@ -168,6 +360,10 @@ impl Query {
         for (name, value) in &self.client.options {
             pairs.append_pair(name, value);
         }
+
+        for (name, value) in &self.params {
+            pairs.append_pair(&format!("param_{name}"), value);
+        }
         drop(pairs);
 
         let mut builder = Request::builder().method(method).uri(url.as_str());
@@ -186,6 +382,8 @@ impl Query {
             builder = builder.header("X-ClickHouse-Key", password);
         }
 
+        builder = builder.header("X-ClickHouse-Query-Id", query_id);
+
         let request = builder
             .body(body)
             .map_err(|err| Error::InvalidParams(Box::new(err)))?;
@@ -195,8 +393,33 @@ impl Query {
     }
 }
 
+/// 对给定 `query_id` 发起一次尽力而为的 `KILL QUERY`。
+///
+/// 这里特意复用一个全新的 [`Query`]（而不是原来那条已经被取消/超时的流）
+/// 去发起 kill，因为原来的连接可能已经在被关闭的路上。是否真的 kill
+/// 成功无法轻易确认，所以失败直接忽略——这只是为了不让服务端白白算一个
+/// 没人要结果的查询，而不是什么强一致的保证。
+async fn kill_query(client: Client, query_id: String) {
+    let _ = client
+        .query("KILL QUERY WHERE query_id = ?")
+        .bind(query_id)
+        .execute()
+        .await;
+}
+
 /// 一个用于发出行数据的游标。
-pub struct RowCursor<T>(RowBinaryCursor<T>);
+pub struct RowCursor<T> {
+    inner: RowBinaryCursor<T>,
+    client: Client,
+    query_id: String,
+    timeout_task: Option<task::JoinHandle<()>>,
+    /// 这次查询是否通过 [`Query::with_timeout`] 请求过取消。只有这种情况下
+    /// 才值得在游标被提前 drop 时补发一次 `KILL QUERY`——调用方只是读了
+    /// 一部分结果就放弃剩下的行（比如 `fetch_one`/`fetch_optional`）完全
+    /// 是正常用法，不该为每一次这样的调用都额外打一次 `KILL QUERY`。
+    cancel_on_drop: bool,
+    finished: bool,
+}
 
 impl<T> RowCursor<T> {
     /// 发出下一行数据。
@@ -204,6 +427,222 @@ impl<T> RowCursor<T> {
     where
         T: Deserialize<'b>,
     {
-        self.0.next().await
+        let row = self.inner.next().await?;
+
+        if row.is_none() {
+            self.finished = true;
+            if let Some(task) = self.timeout_task.take() {
+                task.abort();
+            }
+        }
+
+        Ok(row)
+    }
+
+    /// 这次查询的 `query_id`，可以用来手动发起
+    /// `KILL QUERY WHERE query_id = '<query_id>'`。
+    pub fn query_id(&self) -> &str {
+        &self.query_id
+    }
+
+    /// 截止目前为止这次查询的进度/结果统计快照。
+    ///
+    /// `read_rows`/`read_bytes`/`total_rows_to_read` 来自服务端在扫描过程
+    /// 中发来的 `X-ClickHouse-Progress`（需要先调用 [`Query::with_progress`]
+    /// 打开），`result_rows`/`result_bytes`/`elapsed` 来自流结束时的
+    /// `X-ClickHouse-Summary`。在流读完之前调用，未产生的字段保持为 0。
+    pub fn stats(&self) -> Stats {
+        self.inner.stats()
+    }
+}
+
+/// 一次查询的进度/结果统计快照，参见 [`RowCursor::stats`]。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// 服务端目前已经扫描过的行数。
+    pub read_rows: u64,
+    /// 服务端目前已经扫描过的字节数。
+    pub read_bytes: u64,
+    /// 服务端估计的总共需要扫描的行数（如果它能估计出来的话）。
+    pub total_rows_to_read: u64,
+    /// 结果集的行数。
+    pub result_rows: u64,
+    /// 结果集的字节数。
+    pub result_bytes: u64,
+    /// 服务端报告的查询耗时。
+    pub elapsed: Duration,
+}
+
+impl<T> Drop for RowCursor<T> {
+    fn drop(&mut self) {
+        if let Some(task) = self.timeout_task.take() {
+            task.abort();
+        }
+
+        if self.finished || !self.cancel_on_drop {
+            return;
+        }
+
+        // 只有这次查询设置过 `with_timeout`（意味着调用方确实在意"别让服务端
+        // 白算"）且游标在流结束之前就被 drop 掉时，才尽力而为地补发一次
+        // `KILL QUERY`；否则 `fetch_one`/`fetch_optional` 这类正常提前放弃
+        // 剩余行的用法会被误当成"取消"，对同一个 query_id 多打一次 kill。
+        let client = self.client.clone();
+        let query_id = self.query_id.clone();
+        task::spawn(async move {
+            kill_query(client, query_id).await;
+        });
+    }
+}
+
+/// 一个对 Arrow IPC 流增量解码，发出 [`RecordBatch`] 的游标。
+///
+/// 第一条消息携带 schema 并被缓存下来，后续的 record batch 消息基于该
+/// schema 解码。由于一个 HTTP chunk 可能只包含半条消息，也可能一次性
+/// 带来好几条消息，这里维护一个累积缓冲区，只有集齐一条完整的 IPC 消息
+/// （header + body）才尝试解码，否则继续等待下一个 chunk。
+pub struct ArrowCursor {
+    response: Response,
+    buffer: BytesMut,
+    schema: Option<SchemaRef>,
+    dictionaries: HashMap<i64, ArrayRef>,
+    done: bool,
+}
+
+impl ArrowCursor {
+    pub(crate) fn new(response: Response) -> Self {
+        Self {
+            response,
+            buffer: BytesMut::new(),
+            schema: None,
+            dictionaries: HashMap::new(),
+            done: false,
+        }
+    }
+
+    /// 发出下一个 [`RecordBatch`]；流结束时返回 `None`。
+    pub async fn next(&mut self) -> Result<Option<RecordBatch>> {
+        loop {
+            if let Some(batch) = self.try_decode_one()? {
+                return Ok(Some(batch));
+            }
+
+            if self.done {
+                if !self.buffer.is_empty() {
+                    // 流已经结束，但缓冲区里还剩下一条解不完整的 IPC
+                    // 消息，不能假装什么都没发生地把它当成干净的流末尾。
+                    return Err(Error::Decode("truncated Arrow IPC stream".into()));
+                }
+                return Ok(None);
+            }
+
+            match self.response.chunk().await? {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => self.done = true,
+            }
+        }
+    }
+
+    /// 尝试从缓冲区里解码出一条完整的消息。
+    ///
+    /// Schema 和 dictionary 消息会被直接应用（更新 `self.schema` /
+    /// `self.dictionaries`）而不产生 batch，所以这里是个循环：只要缓冲区
+    /// 里还有完整消息就继续处理，直到产出一个 record batch，或者缓冲区
+    /// 里剩下的数据不够一条完整消息为止。
+    fn try_decode_one(&mut self) -> Result<Option<RecordBatch>> {
+        loop {
+            if self.buffer.len() < 8 {
+                return Ok(None);
+            }
+
+            let marker = u32::from_le_bytes(self.buffer[0..4].try_into().unwrap());
+            if marker != ARROW_CONTINUATION_MARKER {
+                return Err(Error::BadResponse(
+                    "Arrow IPC stream is missing the continuation marker".into(),
+                ));
+            }
+            let meta_len = i32::from_le_bytes(self.buffer[4..8].try_into().unwrap());
+
+            if meta_len == 0 {
+                // 空 schema 消息是流结束标记。
+                self.done = true;
+                self.buffer.clear();
+                return Ok(None);
+            }
+
+            let meta_len = meta_len as usize;
+            const HEADER_LEN: usize = 8;
+
+            if self.buffer.len() < HEADER_LEN + meta_len {
+                return Ok(None); // 元数据还没收全，等下一个 chunk。
+            }
+
+            let message = root_as_message(&self.buffer[HEADER_LEN..HEADER_LEN + meta_len])
+                .map_err(|err| Error::BadResponse(err.to_string()))?;
+            let body_len = message.bodyLength() as usize;
+            let total_len = HEADER_LEN + meta_len + body_len;
+
+            if self.buffer.len() < total_len {
+                return Ok(None); // body 还没收全。
+            }
+
+            let body = self.buffer[HEADER_LEN + meta_len..total_len].to_vec();
+            let version = message.version();
+            let header_type = message.header_type();
+
+            let batch = match header_type {
+                MessageHeader::Schema => {
+                    let ipc_schema = message
+                        .header_as_schema()
+                        .ok_or_else(|| Error::BadResponse("malformed schema message".into()))?;
+                    self.schema = Some(Arc::new(fb_to_schema(ipc_schema)));
+                    None
+                }
+                MessageHeader::DictionaryBatch => {
+                    let schema = self.schema.clone().ok_or_else(|| {
+                        Error::BadResponse("dictionary batch received before schema".into())
+                    })?;
+                    let batch = message.header_as_dictionary_batch().ok_or_else(|| {
+                        Error::BadResponse("malformed dictionary batch message".into())
+                    })?;
+                    read_dictionary(
+                        &IoCursor::new(body).into_inner().into(),
+                        batch,
+                        &schema,
+                        &mut self.dictionaries,
+                        &version,
+                    )
+                    .map_err(|err| Error::BadResponse(err.to_string()))?;
+                    None
+                }
+                MessageHeader::RecordBatch => {
+                    let schema = self.schema.clone().ok_or_else(|| {
+                        Error::BadResponse("record batch received before schema".into())
+                    })?;
+                    let batch = message.header_as_record_batch().ok_or_else(|| {
+                        Error::BadResponse("malformed record batch message".into())
+                    })?;
+                    Some(
+                        read_record_batch(
+                            &body.into(),
+                            batch,
+                            schema,
+                            &self.dictionaries,
+                            None,
+                            &version,
+                        )
+                        .map_err(|err| Error::BadResponse(err.to_string()))?,
+                    )
+                }
+                _ => None,
+            };
+
+            self.buffer.advance(total_len);
+
+            if batch.is_some() {
+                return Ok(batch);
+            }
+            // schema/dictionary 消息不产生 batch，继续看缓冲区里是否还有下一条消息。
+        }
     }
 }
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use hyper::{body::HttpBody, client::ResponseFuture, HeaderMap};
+
+use crate::{
+    error::{Error, Result},
+    query::Stats,
+    Compression,
+};
+
+/// 对一次 HTTP 响应的包装：负责把状态码/header 翻译成 [`Error`]，并在
+/// 读取响应体的同时把服务端通过 `X-ClickHouse-Progress`/
+/// `X-ClickHouse-Summary` header 上报的统计信息累积起来。
+///
+/// 这两个 header 到达的方式不一样：`response.headers()` 是响应头部，在
+/// 响应体开始读之前就已经定型，之后不会再变，所以只在这里读一次就够了
+/// ——实践中至多能从里面看到一次性给全的 `X-ClickHouse-Summary`。真正
+/// 随着扫描进度持续上报的 `X-ClickHouse-Progress`，以及流式场景下只有
+/// 在结果集完全产出之后才能确定的 `X-ClickHouse-Summary`，服务端是作为
+/// HTTP trailer（`hyper::body::HttpBody::trailers`）在 body 读完之后才
+/// 发出来的，必须等 `data()` 返回 `None` 之后单独去拉一次。
+pub(crate) struct Response {
+    future: ResponseFuture,
+    inner: Option<hyper::Response<hyper::Body>>,
+    compression: Compression,
+    stats: Stats,
+}
+
+impl Response {
+    pub(crate) fn new(future: ResponseFuture, compression: Compression) -> Self {
+        Self {
+            future,
+            inner: None,
+            compression,
+            stats: Stats::default(),
+        }
+    }
+
+    async fn ensure_started(&mut self) -> Result<&mut hyper::Response<hyper::Body>> {
+        if self.inner.is_none() {
+            let response = (&mut self.future).await.map_err(|err| {
+                Error::BadResponse(err.to_string())
+            })?;
+
+            if !response.status().is_success() {
+                let reason = response
+                    .status()
+                    .canonical_reason()
+                    .unwrap_or("unknown error")
+                    .to_string();
+                return Err(Error::BadResponse(reason));
+            }
+
+            let headers = response.headers().clone();
+            self.inner = Some(response);
+            self.merge_headers(&headers);
+        }
+
+        Ok(self.inner.as_mut().unwrap())
+    }
+
+    /// 拉取下一个原始字节块；流结束时返回 `None`。
+    pub(crate) async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        let response = self.ensure_started().await?;
+        let chunk = response.body_mut().data().await.transpose().map_err(|err| {
+            Error::BadResponse(err.to_string())
+        })?;
+
+        if chunk.is_none() {
+            // body 读完了：`X-ClickHouse-Progress`/`X-ClickHouse-Summary`
+            // 在真正的流式响应里是作为 trailer 发出来的，只有现在才能
+            // 拿到，之前响应头部里那份是不会再更新的。
+            if let Some(trailers) = response
+                .body_mut()
+                .trailers()
+                .await
+                .map_err(|err| Error::BadResponse(err.to_string()))?
+            {
+                self.merge_headers(&trailers);
+            }
+        }
+
+        Ok(chunk)
+    }
+
+    /// 把响应体读完并丢弃（用于不关心结果集的 `execute`）。
+    pub(crate) async fn finish(mut self) -> Result<()> {
+        while self.chunk().await?.is_some() {}
+        Ok(())
+    }
+
+    /// 目前为止从 header 里看到的统计快照。
+    pub(crate) fn stats(&self) -> Stats {
+        self.stats.clone()
+    }
+
+    fn merge_headers(&mut self, headers: &HeaderMap) {
+        // 同一个 header 可能出现多次，按出现顺序依次合并，让最新的值留下。
+        for value in headers.get_all("X-ClickHouse-Progress") {
+            if let Ok(text) = value.to_str() {
+                merge_progress(&mut self.stats, text);
+            }
+        }
+
+        if let Some(value) = headers.get("X-ClickHouse-Summary") {
+            if let Ok(text) = value.to_str() {
+                merge_summary(&mut self.stats, text);
+            }
+        }
+    }
+}
+
+/// `X-ClickHouse-Progress` 的内容形如
+/// `{"read_rows":"100","read_bytes":"1000","total_rows_to_read":"1000"}`，
+/// 是服务端在扫描过程中持续发来的累计值（需要
+/// [`crate::query::Query::with_progress`] 打开），所以新值直接覆盖旧值。
+fn merge_progress(stats: &mut Stats, text: &str) {
+    for (key, value) in parse_json_object(text) {
+        let value: u64 = match value.parse() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        match key {
+            "read_rows" => stats.read_rows = value,
+            "read_bytes" => stats.read_bytes = value,
+            "total_rows_to_read" => stats.total_rows_to_read = value,
+            _ => {}
+        }
+    }
+}
+
+/// `X-ClickHouse-Summary` 内容形如
+/// `{"read_rows":"100","read_bytes":"1000","written_rows":"0",
+/// "written_bytes":"0","total_rows_to_read":"0","result_rows":"100",
+/// "result_bytes":"4000","elapsed_ns":"123456"}`，只在流结束时发一次。
+fn merge_summary(stats: &mut Stats, text: &str) {
+    for (key, value) in parse_json_object(text) {
+        match key {
+            "read_rows" => stats.read_rows = value.parse().unwrap_or(stats.read_rows),
+            "read_bytes" => stats.read_bytes = value.parse().unwrap_or(stats.read_bytes),
+            "total_rows_to_read" => {
+                stats.total_rows_to_read = value.parse().unwrap_or(stats.total_rows_to_read)
+            }
+            "result_rows" => stats.result_rows = value.parse().unwrap_or(stats.result_rows),
+            "result_bytes" => stats.result_bytes = value.parse().unwrap_or(stats.result_bytes),
+            "elapsed_ns" => {
+                if let Ok(ns) = value.parse::<u64>() {
+                    stats.elapsed = Duration::from_nanos(ns);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 这些 header 总是一层扁平的 `{"key":"value", ...}`，值永远是带引号的
+/// 数字字符串，没有必要为这么窄的一个格式拉一整个 JSON 解析器进来，手写
+/// 一个只认这种形状的小解析器就够了。
+fn parse_json_object(text: &str) -> Vec<(&str, &str)> {
+    let inner = text.trim().trim_start_matches('{').trim_end_matches('}');
+    let mut pairs = Vec::new();
+
+    for entry in inner.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let Some((key, value)) = entry.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().trim_matches('"');
+        let value = value.trim().trim_matches('"');
+        pairs.push((key, value));
+    }
+
+    pairs
+}
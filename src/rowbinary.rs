@@ -0,0 +1,194 @@
+use serde::{
+    de::{DeserializeSeed, Visitor},
+    Deserialize, Deserializer as SerdeDeserializer,
+};
+
+use crate::error::{Error, Result};
+
+/// 从 `input` 开头解码出一个 `T`（RowBinary 编码），并把 `input` 前移到
+/// 消费掉的字节之后。
+///
+/// 如果现有字节不够解出完整的一条记录，返回 [`Error::NotEnoughData`]——
+/// 调用方据此知道要等更多数据到达，而不是把它当成格式错误处理；其它
+/// 任何错误都代表数据是真的有问题（类型不对、字符串不是合法 UTF-8 等），
+/// 需要原样传播，不能被当成"数据不够"而静默吞掉。
+pub(crate) fn deserialize_from<'de, T>(input: &mut &'de [u8]) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let mut de = RowBinaryDeserializer { input };
+    let value = T::deserialize(&mut de)?;
+    *input = de.input;
+    Ok(value)
+}
+
+struct RowBinaryDeserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> RowBinaryDeserializer<'de> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'de [u8]> {
+        if self.input.len() < len {
+            return Err(Error::NotEnoughData);
+        }
+        let (head, tail) = self.input.split_at(len);
+        self.input = tail;
+        Ok(head)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_i8(&mut self) -> Result<i8> {
+        Ok(self.read_bytes(1)?[0] as i8)
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    fn read_i16(&mut self) -> Result<i16> {
+        Ok(i16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    fn read_i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    fn read_f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    /// 字符串长度用 LEB128 编码。
+    fn read_len(&mut self) -> Result<usize> {
+        let mut result = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            result |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_str(&mut self) -> Result<&'de str> {
+        let len = self.read_len()?;
+        let bytes = self.read_bytes(len)?;
+        std::str::from_utf8(bytes).map_err(|err| Error::Decode(err.to_string()))
+    }
+}
+
+impl<'de, 'a> SerdeDeserializer<'de> for &'a mut RowBinaryDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Decode(
+            "RowBinary requires a concrete type; `deserialize_any` isn't supported".into(),
+        ))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(self.read_u8()? != 0)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(self.read_u8()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(self.read_u16()?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(self.read_u32()?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u64(self.read_u64()?)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(self.read_i8()?)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16(self.read_i16()?)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(self.read_i32()?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(self.read_i64()?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(self.read_f32()?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(self.read_f64()?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_borrowed_str(self.read_str()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_borrowed_str(self.read_str()?)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(RowAccess {
+            de: self,
+            remaining: fields.len(),
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        char bytes byte_buf option unit unit_struct
+        newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+struct RowAccess<'a, 'de> {
+    de: &'a mut RowBinaryDeserializer<'de>,
+    remaining: usize,
+}
+
+impl<'de, 'a> serde::de::SeqAccess<'de> for RowAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt::Write;
+
+use crate::error::{Error, Result};
+
+/// 可以被绑定进查询的值。
+///
+/// 两处地方会用到它，写出来的文本完全不同：
+/// - [`crate::query::Query::bind`] 把值转义成可以直接拼进 SQL 文本的字面量
+///   （字符串要加引号并转义特殊字符），替换模板里的 `?`。
+/// - [`crate::query::Query::param`] 把值写成 ClickHouse 命名参数
+///   （`{name:Type}`）期望的裸文本格式，不加引号也不转义，因为这是作为
+///   `param_<name>=<value>` URL 参数单独传给服务端、由服务端自己按类型解析
+///   的，客户端再转义反而会把字面的引号传进去，parse 出错。
+pub trait Bind {
+    /// 转义成 SQL 字面量，用于 [`crate::query::Query::bind`]。
+    fn write(&self, dst: &mut impl Write) -> std::fmt::Result;
+
+    /// 写成裸文本，用于 [`crate::query::Query::param`]。默认实现复用
+    /// [`Bind::write`]，适用于数字这类本来就没有引号问题的类型；
+    /// 字符串/日期等类型需要单独重载。
+    fn write_param(&self, dst: &mut impl Write) -> std::fmt::Result {
+        self.write(dst)
+    }
+}
+
+impl Bind for &str {
+    fn write(&self, dst: &mut impl Write) -> std::fmt::Result {
+        write!(dst, "'")?;
+        for ch in self.chars() {
+            match ch {
+                '\'' => write!(dst, "\\'")?,
+                '\\' => write!(dst, "\\\\")?,
+                _ => write!(dst, "{ch}")?,
+            }
+        }
+        write!(dst, "'")
+    }
+
+    fn write_param(&self, dst: &mut impl Write) -> std::fmt::Result {
+        // 命名参数走的是 ClickHouse 自己的文本格式，不是 SQL 字面量，
+        // 所以这里不加引号、不转义，原样写出来。
+        write!(dst, "{self}")
+    }
+}
+
+impl Bind for String {
+    fn write(&self, dst: &mut impl Write) -> std::fmt::Result {
+        self.as_str().write(dst)
+    }
+
+    fn write_param(&self, dst: &mut impl Write) -> std::fmt::Result {
+        self.as_str().write_param(dst)
+    }
+}
+
+macro_rules! impl_bind_for_number {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Bind for $ty {
+                fn write(&self, dst: &mut impl Write) -> std::fmt::Result {
+                    write!(dst, "{self}")
+                }
+            }
+        )*
+    };
+}
+
+impl_bind_for_number!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// 增量构造最终要发给服务端的 SQL 文本。
+///
+/// 负责替换模板里的 `?fields` 和位置 `?` 占位符；`{name:Type}` 这样的
+/// 命名参数占位符原样保留，交给服务端解析（对应的值通过
+/// `param_<name>` URL 参数单独传递，见 [`crate::query::Query::param`]）。
+#[derive(Clone)]
+pub(crate) struct SqlBuilder {
+    template: String,
+    args: Vec<String>,
+    fields: Option<String>,
+}
+
+impl SqlBuilder {
+    pub(crate) fn new(template: &str) -> Self {
+        Self {
+            template: template.to_string(),
+            args: Vec::new(),
+            fields: None,
+        }
+    }
+
+    pub(crate) fn bind_arg(&mut self, value: impl Bind) {
+        let mut text = String::new();
+        let _ = value.write(&mut text);
+        self.args.push(text);
+    }
+
+    /// 不做任何事：不会从 `T` 取列名，`fields` 保持为 `None`，所以
+    /// [`SqlBuilder::finish`] 总是把 `?fields` 展开成 `*`。
+    pub(crate) fn bind_fields<T>(&mut self) {}
+
+    pub(crate) fn append(&mut self, suffix: &str) {
+        self.template.push_str(suffix);
+    }
+
+    pub(crate) fn finish(&self) -> Result<String> {
+        let mut result = String::with_capacity(self.template.len());
+        let mut args = self.args.iter();
+        let mut rest = self.template.as_str();
+
+        while let Some(pos) = rest.find('?') {
+            result.push_str(&rest[..pos]);
+            rest = &rest[pos + 1..];
+
+            if let Some(after_fields) = rest.strip_prefix("fields") {
+                result.push_str(self.fields.as_deref().unwrap_or("*"));
+                rest = after_fields;
+                continue;
+            }
+
+            match args.next() {
+                Some(arg) => result.push_str(arg),
+                None => {
+                    return Err(Error::InvalidParams(Box::new(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "not enough arguments bound for this query",
+                    ))))
+                }
+            }
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+}
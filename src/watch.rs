@@ -0,0 +1,171 @@
+use serde::Deserialize;
+
+use crate::{
+    cursor::{JsonCursor, RowBinaryCursor},
+    error::Result,
+    row::Row,
+    Client,
+};
+
+/// 一个 `WATCH` 查询的构造器，通过 `CREATE LIVE VIEW` + `WATCH` 订阅一张表
+/// （或一个查询）的变更，参见 [`Client::watch`]。
+#[must_use]
+#[derive(Clone)]
+pub struct Watch {
+    client: Client,
+    sql: String,
+}
+
+impl Watch {
+    pub(crate) fn new(client: &Client, sql: impl Into<String>) -> Self {
+        Self {
+            client: client.clone(),
+            sql: sql.into(),
+        }
+    }
+
+    /// 只关心版本号变了没有，不取回变更后的整行数据。
+    ///
+    /// 返回一个独立的 [`WatchOnlyEvents`] 而不是在 `Watch` 自身上翻一个
+    /// 标志位，是因为这之后 `fetch_one`/`fetch` 产出的就不再是
+    /// `(u64, T)`，而是裸的 `u64`——`T` 根本没有着落，硬塞一个泛型参数
+    /// 只会让调用方被迫写一个永远用不上、也推导不出来的 turbofish。
+    pub fn only_events(self) -> WatchOnlyEvents {
+        WatchOnlyEvents { watch: self }
+    }
+
+    /// 按版本号分组：底层游标逐行产出的 `(version, row)` 会被按 `version`
+    /// 累积起来，同一个版本号下的所有行攒成一个 `Vec` 后一起作为一次刷新
+    /// 返回，而不是像 [`Watch::fetch`] 那样逐行单独产出。这样消费方可以
+    /// 把一次 live view 刷新当成一个一致的快照来处理，不用自己在外面按
+    /// `version` 重新分组。
+    ///
+    /// 流结束时，攒到一半的最后一组也会被照常 flush 出去。
+    pub fn groups(self) -> WatchGroups {
+        WatchGroups { watch: self }
+    }
+
+    /// 执行查询，返回一个逐行产出 `(version, row)` 的 [`WatchCursor`]。
+    pub fn fetch<T: Row>(self) -> Result<WatchCursor<T>> {
+        let response = self.client.do_watch(&self.sql, false)?;
+        Ok(WatchCursor(JsonCursor::new(response)))
+    }
+
+    /// 执行查询并只返回第一行及其版本号；同一次刷新里的其余行不会返回。
+    pub async fn fetch_one<T>(self) -> Result<(u64, T)>
+    where
+        T: Row + for<'b> Deserialize<'b>,
+    {
+        match self.fetch()?.next().await? {
+            Some(row) => Ok(row),
+            None => Err(crate::error::Error::RowNotFound),
+        }
+    }
+}
+
+/// [`Watch::only_events`] 返回的构造器：只关心版本号有没有变，不取回整行
+/// 数据，所以这里的游标/`fetch_one` 都不带泛型参数。
+#[must_use]
+pub struct WatchOnlyEvents {
+    watch: Watch,
+}
+
+impl WatchOnlyEvents {
+    /// 执行查询，返回一个逐个发出版本号的 [`EventsCursor`]。
+    pub fn fetch(self) -> Result<EventsCursor> {
+        let response = self.watch.client.do_watch(&self.watch.sql, true)?;
+        Ok(EventsCursor(JsonCursor::new(response)))
+    }
+
+    /// 执行查询并只返回第一次刷新的版本号。
+    pub async fn fetch_one(self) -> Result<u64> {
+        match self.fetch()?.next().await? {
+            Some(version) => Ok(version),
+            None => Err(crate::error::Error::RowNotFound),
+        }
+    }
+}
+
+/// 一个逐个发出版本号的 `WATCH ... EVENTS` 游标，参见 [`Watch::only_events`]。
+///
+/// `EVENTS` 模式下服务端每行只发版本号本身（不是 `[version, row]` 这样的
+/// 二元组），所以底层游标直接按 `u64` 解码，而不是复用 `fetch()` 用的
+/// `JsonCursor<(u64, T)>`。
+pub struct EventsCursor(JsonCursor<u64>);
+
+impl EventsCursor {
+    /// 发出下一次刷新的版本号。
+    pub async fn next(&mut self) -> Result<Option<u64>> {
+        self.0.next().await
+    }
+}
+
+/// 一个逐行发出 `(version, row)` 的 `WATCH` 游标。
+pub struct WatchCursor<T>(JsonCursor<(u64, T)>);
+
+impl<T> WatchCursor<T> {
+    /// 发出下一次刷新中的下一行。
+    pub async fn next<'a, 'b: 'a>(&'a mut self) -> Result<Option<(u64, T)>>
+    where
+        T: Deserialize<'b>,
+    {
+        self.0.next().await
+    }
+}
+
+/// [`Watch::groups`] 返回的构造器：按版本号把行分组，一次刷新的所有行
+/// 攒成一个 `Vec` 一起发出。
+#[must_use]
+pub struct WatchGroups {
+    watch: Watch,
+}
+
+impl WatchGroups {
+    /// 执行查询，返回按版本号分组的 [`WatchGroupCursor`]。
+    pub fn fetch<T: Row>(self) -> Result<WatchGroupCursor<T>> {
+        Ok(WatchGroupCursor {
+            inner: self.watch.fetch()?,
+            pending: None,
+        })
+    }
+}
+
+/// 一个按版本号分组，发出 `(version, Vec<row>)` 的 `WATCH` 游标，参见
+/// [`Watch::groups`]。
+pub struct WatchGroupCursor<T> {
+    inner: WatchCursor<T>,
+    // 当前已经读到、但还不知道是否已经集齐的一组：`(version, 已攒到的行)`。
+    pending: Option<(u64, Vec<T>)>,
+}
+
+impl<T> WatchGroupCursor<T> {
+    /// 发出下一组 `(version, rows)`；流结束时返回 `None`（包括把最后一组
+    /// 不完整的数据 flush 出去之后）。
+    pub async fn next<'a, 'b: 'a>(&'a mut self) -> Result<Option<(u64, Vec<T>)>>
+    where
+        T: Deserialize<'b>,
+    {
+        loop {
+            match self.inner.next().await? {
+                Some((version, row)) => match &mut self.pending {
+                    Some((current_version, rows)) if *current_version == version => {
+                        rows.push(row);
+                    }
+                    Some(_) => {
+                        // 版本号变了，当前这组已经集齐，flush 掉，把新行
+                        // 留作下一组的第一行。
+                        let finished = self.pending.replace((version, vec![row])).unwrap();
+                        return Ok(Some(finished));
+                    }
+                    None => {
+                        self.pending = Some((version, vec![row]));
+                    }
+                },
+                None => {
+                    // 流结束了，把攒到一半的最后一组 flush 出去。
+                    return Ok(self.pending.take());
+                }
+            }
+        }
+    }
+}